@@ -0,0 +1,191 @@
+//! Companion proc-macro crate for the `rust-basic` MCP server template.
+//!
+//! Without this crate, every tool has to be maintained in two disconnected
+//! places: the JSON `inputSchema` literal in `list_tools()` and the
+//! argument-parsing arm in `call_tool()`. `#[mcp_tool]` writes a tool once,
+//! as a plain async fn with typed parameters, and generates both from that
+//! single source of truth — the pattern `jsonrpc_derive`'s `rpc_impl` uses
+//! for karyon_jsonrpc.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, FnArg, Ident, ItemFn, Pat, PatType, Type};
+
+/// Marks an async fn as an MCP tool.
+///
+/// ```ignore
+/// #[mcp_tool(description = "Say hello to someone")]
+/// async fn hello(name: String) -> anyhow::Result<serde_json::Value> {
+///     Ok(serde_json::json!({ "content": [{ "type": "text", "text": format!("Hello, {name}!") }] }))
+/// }
+/// ```
+///
+/// Expands to the original function (renamed, so it keeps running exactly
+/// as written) plus an `inventory::submit!` registration carrying the
+/// inferred `inputSchema` and a dispatch closure, so `tools::list_tools()`
+/// and `tools::call_tool()` can never drift out of sync with the fn
+/// signature.
+#[proc_macro_attribute]
+pub fn mcp_tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let description = parse_description(attr);
+    let tool_fn = parse_macro_input!(item as ItemFn);
+
+    let tool_name = tool_fn.sig.ident.to_string();
+    let impl_ident = format_ident!("__mcp_tool_impl_{}", tool_fn.sig.ident);
+
+    let params: Vec<(&Ident, &Type, bool)> = tool_fn
+        .sig
+        .inputs
+        .iter()
+        .map(|arg| match arg {
+            FnArg::Typed(PatType { pat, ty, .. }) => {
+                let ident = match pat.as_ref() {
+                    Pat::Ident(pat_ident) => &pat_ident.ident,
+                    _ => panic!("#[mcp_tool] arguments must be simple identifiers"),
+                };
+                let (inner_ty, optional) = unwrap_option(ty);
+                (ident, inner_ty, optional)
+            }
+            FnArg::Receiver(_) => panic!("#[mcp_tool] cannot be applied to methods"),
+        })
+        .collect();
+
+    let schema_properties = params.iter().map(|(ident, ty, _)| {
+        let name = ident.to_string();
+        let json_type = json_schema_type(ty);
+        quote! { properties.insert(#name.to_string(), serde_json::json!({ "type": #json_type })); }
+    });
+
+    let required_names = params
+        .iter()
+        .filter(|(_, _, optional)| !optional)
+        .map(|(ident, _, _)| ident.to_string());
+
+    let extractors = params.iter().map(|(ident, ty, optional)| {
+        let name = ident.to_string();
+        extractor(ident, &name, ty, *optional)
+    });
+
+    let call_args = params.iter().map(|(ident, _, _)| quote! { #ident });
+
+    let mut inner_fn = tool_fn.clone();
+    inner_fn.sig.ident = impl_ident.clone();
+
+    let registered_name = tool_name.clone();
+    let expanded = quote! {
+        #inner_fn
+
+        inventory::submit! {
+            crate::tools::ToolDef {
+                name: #registered_name,
+                schema: || {
+                    let mut properties = serde_json::Map::new();
+                    #(#schema_properties)*
+                    let required: Vec<&str> = vec![#(#required_names),*];
+                    serde_json::json!({
+                        "name": #registered_name,
+                        "description": #description,
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": properties,
+                            "required": required,
+                        }
+                    })
+                },
+                // Extraction runs synchronously, *before* the future is
+                // built, so only owned values (never `arguments` itself) end
+                // up captured by `async move`. Capturing `&Value` directly
+                // here would tie the returned future's type to the input's
+                // lifetime, which can't satisfy the `fn(&Value) -> Pin<Box<dyn
+                // Future<..> + Send>>` signature `ToolDef.handler` requires
+                // for every possible input lifetime.
+                handler: |arguments| {
+                    #(#extractors)*
+                    Box::pin(async move {
+                        #impl_ident(#(#call_args),*)
+                            .await
+                            .map_err(crate::tools::ToolError::from)
+                    })
+                },
+            }
+        }
+    };
+
+    expanded.into()
+}
+
+fn parse_description(attr: TokenStream) -> String {
+    let attr = attr.to_string();
+    attr.split('=')
+        .nth(1)
+        .map(|s| s.trim().trim_matches('"').to_string())
+        .unwrap_or_default()
+}
+
+fn unwrap_option(ty: &Type) -> (&Type, bool) {
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            if segment.ident == "Option" {
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
+                        return (inner, true);
+                    }
+                }
+            }
+        }
+    }
+    (ty, false)
+}
+
+fn json_schema_type(ty: &Type) -> &'static str {
+    let name = quote!(#ty).to_string();
+    match name.as_str() {
+        "String" | "str" | "& str" => "string",
+        "bool" => "boolean",
+        "f32" | "f64" | "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "usize"
+        | "isize" => "number",
+        _ => "object",
+    }
+}
+
+fn extractor(ident: &Ident, name: &str, ty: &Type, optional: bool) -> TokenStream2 {
+    let json_type = json_schema_type(ty);
+    // Primitives read straight off the `Value`; anything else (a
+    // `#[derive(Deserialize)]` args struct, a `Vec<T>`, ...) goes through
+    // `serde_json::from_value` so struct-typed parameters actually work,
+    // not just the handful of types with a blanket `From<Value>` impl.
+    let accessor = match json_type {
+        "string" => quote! { |v: &serde_json::Value| v.as_str().map(|v| v.to_string()) },
+        "boolean" => quote! { |v: &serde_json::Value| v.as_bool() },
+        "number" => quote! { |v: &serde_json::Value| v.as_f64().map(|v| v as #ty) },
+        _ => quote! { |v: &serde_json::Value| serde_json::from_value::<#ty>(v.clone()).ok() },
+    };
+
+    if optional {
+        quote! {
+            let #ident = arguments.get(#name).and_then(#accessor);
+        }
+    } else {
+        // No `?` here: this runs outside the `async move` block, in a
+        // closure body that must return a `Pin<Box<dyn Future<..>>>`, not a
+        // `Result`. A missing/invalid argument instead returns early with an
+        // already-failed future of the same type the success path produces,
+        // carrying a `ToolError::InvalidParams` so it surfaces as `-32602`
+        // rather than falling through `ToolError::from(anyhow::Error)` into
+        // a generic `-32603` internal error.
+        quote! {
+            let #ident = match arguments.get(#name).and_then(#accessor) {
+                Some(value) => value,
+                None => {
+                    return Box::pin(async move {
+                        Err(crate::tools::ToolError::invalid_param(
+                            #name,
+                            concat!("Missing or invalid parameter '", #name, "'"),
+                        ))
+                    })
+                }
+            };
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use anyhow::Result;
+use std::sync::Arc;
 use tracing::{info, error};
 use tracing_subscriber;
 
 mod server;
 mod tools;
 mod resources;
+mod transport;
 
 use server::MCPServer;
 
@@ -21,8 +23,8 @@ async fn main() -> Result<()> {
     info!("Starting {{serverName}} MCP server...");
 
     // Create and run the MCP server
-    let server = MCPServer::new();
-    
+    let server = Arc::new(MCPServer::new());
+
     match server.run().await {
         Ok(_) => {
             info!("{{serverName}} MCP server stopped gracefully");
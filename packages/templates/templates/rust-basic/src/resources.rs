@@ -0,0 +1,37 @@
+use serde_json::{json, Value};
+
+use crate::tools::ToolError;
+
+/// Static resource registry for the template. A generated server will
+/// typically back this with files, database rows, or another MCP server;
+/// this mirrors `tools::list_tools()`/`tools::call_tool()` in shape so the
+/// two registries stay easy to read side by side.
+pub fn list_resources() -> Vec<Value> {
+    vec![json!({
+        "uri": "example://greeting",
+        "name": "Example greeting",
+        "description": "A static example resource",
+        "mimeType": "text/plain"
+    })]
+}
+
+/// Reads one resource by URI. Reuses [`ToolError`] rather than a parallel
+/// enum, so `server::handle_read_resource` maps failures to the same
+/// `-32602`/`-32601`/`-32603` codes (and `data` payload) `call_tool` does,
+/// instead of collapsing everything into "internal error".
+pub async fn read_resource(params: Value) -> Result<Value, ToolError> {
+    let uri = params["uri"]
+        .as_str()
+        .ok_or_else(|| ToolError::invalid_param("uri", "Missing 'uri' parameter"))?;
+
+    match uri {
+        "example://greeting" => Ok(json!({
+            "contents": [{
+                "uri": uri,
+                "mimeType": "text/plain",
+                "text": "Hello from {{serverName}}!"
+            }]
+        })),
+        _ => Err(ToolError::NotFound(uri.to_string())),
+    }
+}
@@ -1,15 +1,42 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::io::{self, BufRead, Write};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex as AsyncMutex};
 use tracing::{debug, info, warn};
 
-use crate::tools;
 use crate::resources;
+use crate::tools::{self, ToolError};
+use crate::transport::{self, Framing};
+
+/// Upper bound on how long a single request is allowed to run before the
+/// response queue gives up on it. Without this, a handler that hangs would
+/// never send on `response_tx`, and every later sequence number
+/// already sitting in `run_writer`'s `pending` map would be stuck behind it
+/// forever.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Identifies a single `resources/subscribe` call so it can be matched up
+/// with a later `resources/unsubscribe`.
+type SubscriptionId = String;
+
+/// Tracks which subscriptions are watching each resource URI, so that a
+/// `notifications/resources/updated` message can be routed to the right
+/// subscribers.
+type SubscriptionRegistry = Arc<AsyncMutex<HashMap<String, HashSet<SubscriptionId>>>>;
 
-#[derive(Debug)]
 pub struct MCPServer {
     name: String,
     version: String,
+    subscriptions: SubscriptionRegistry,
+    /// Shared handle to stdout so notifications can be interleaved with
+    /// request/response traffic without corrupting either line.
+    writer: Arc<AsyncMutex<io::Stdout>>,
+    next_subscription_id: AtomicU64,
+    framing: Box<dyn Framing>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -34,6 +61,15 @@ struct Response {
 struct ErrorResponse {
     code: i32,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<serde_json::Value>,
+}
+
+/// What one line of input resolved to, queued up for the writer task to
+/// emit once it's this request's turn in the ordered response queue.
+enum OutboundMessage {
+    Single(Response),
+    Batch(Vec<Response>),
 }
 
 impl MCPServer {
@@ -41,67 +77,329 @@ impl MCPServer {
         Self {
             name: "{{serverName}}".to_string(),
             version: "0.1.0".to_string(),
+            subscriptions: Arc::new(AsyncMutex::new(HashMap::new())),
+            writer: Arc::new(AsyncMutex::new(io::stdout())),
+            next_subscription_id: AtomicU64::new(1),
+            framing: transport::framing_from_env(),
         }
     }
 
-    pub async fn run(&self) -> Result<()> {
-        info!("MCP server {} v{} ready", self.name, self.version);
-        
+    /// Reads requests from stdin and dispatches each on its own Tokio task,
+    /// so one slow tool call can no longer stall every other request on the
+    /// connection. Outbound writes are serialized through a dedicated writer
+    /// task fed by a channel; a small pending-sequence queue reorders
+    /// out-of-order completions so responses are still written in the order
+    /// their requests arrived, even though they're handled concurrently.
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        info!(
+            "MCP server {} v{} ready (framing: {})",
+            self.name,
+            self.version,
+            self.framing.name()
+        );
+
+        let (response_tx, response_rx) =
+            mpsc::unbounded_channel::<(u64, Option<OutboundMessage>)>();
+
+        let writer_server = Arc::clone(&self);
+        let writer_task = tokio::spawn(async move { writer_server.run_writer(response_rx).await });
+
         let stdin = io::stdin();
-        let mut stdout = io::stdout();
-        
-        for line in stdin.lock().lines() {
-            let line = line?;
-            debug!("Received: {}", line);
-            
-            match serde_json::from_str::<Request>(&line) {
+        let mut handle = stdin.lock();
+        let mut next_seq: u64 = 0;
+
+        while let Some(message) = self.framing.read_message(&mut handle)? {
+            debug!("Received: {}", message);
+
+            let seq = next_seq;
+            next_seq += 1;
+
+            let task_server = Arc::clone(&self);
+            let response_tx = response_tx.clone();
+            let handler_task = tokio::spawn(async move { task_server.handle_line(&message).await });
+            let abort_handle = handler_task.abort_handle();
+            tokio::spawn(async move {
+                let outbound = match tokio::time::timeout(REQUEST_TIMEOUT, handler_task).await {
+                    Ok(Ok(outbound)) => outbound,
+                    Ok(Err(e)) => {
+                        warn!("Request handler task panicked: {}", e);
+                        Some(OutboundMessage::Single(Self::error_response(
+                            None,
+                            -32603,
+                            "Internal error: request handler panicked",
+                        )))
+                    }
+                    Err(_) => {
+                        warn!("Request handler timed out after {:?}", REQUEST_TIMEOUT);
+                        abort_handle.abort();
+                        Some(OutboundMessage::Single(Self::error_response(
+                            None,
+                            -32603,
+                            "Internal error: request handler timed out",
+                        )))
+                    }
+                };
+                // The writer task only goes away once every sender (including
+                // this one) has dropped, so it is always still around here.
+                let _ = response_tx.send((seq, outbound));
+            });
+        }
+
+        drop(response_tx);
+        writer_task.await?;
+
+        Ok(())
+    }
+
+    /// Drains the response-queue channel, holding out-of-order completions
+    /// in `pending` until every lower sequence number has been written, then
+    /// flushing them in request order.
+    async fn run_writer(
+        &self,
+        mut response_rx: mpsc::UnboundedReceiver<(u64, Option<OutboundMessage>)>,
+    ) {
+        let mut pending: BTreeMap<u64, Option<OutboundMessage>> = BTreeMap::new();
+        let mut next_seq: u64 = 0;
+
+        while let Some((seq, outbound)) = response_rx.recv().await {
+            pending.insert(seq, outbound);
+
+            while let Some(outbound) = pending.remove(&next_seq) {
+                next_seq += 1;
+                let result = match outbound {
+                    Some(OutboundMessage::Single(response)) => self.write_message(&response).await,
+                    Some(OutboundMessage::Batch(responses)) => self.write_message(&responses).await,
+                    None => Ok(()),
+                };
+                if let Err(e) = result {
+                    warn!("Failed to write response: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Handles one line of input, which per JSON-RPC 2.0 may be either a
+    /// single request object or a *batch*: a JSON array of request objects.
+    async fn handle_line(&self, line: &str) -> Option<OutboundMessage> {
+        let value: serde_json::Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("Failed to parse request: {}", e);
+                return Some(OutboundMessage::Single(Self::error_response(
+                    None,
+                    -32700,
+                    "Parse error",
+                )));
+            }
+        };
+
+        match value {
+            serde_json::Value::Array(items) => self.handle_batch(items).await,
+            single => match serde_json::from_value::<Request>(single) {
+                Ok(request) => self
+                    .handle_request(request)
+                    .await
+                    .map(OutboundMessage::Single),
+                Err(e) => {
+                    warn!("Invalid request: {}", e);
+                    Some(OutboundMessage::Single(Self::error_response(
+                        None,
+                        -32600,
+                        "Invalid Request",
+                    )))
+                }
+            },
+        }
+    }
+
+    /// Handles a batch of requests. An empty batch is itself invalid per
+    /// spec; a batch containing only notifications produces no output at
+    /// all, since none of its members have a response.
+    async fn handle_batch(&self, items: Vec<serde_json::Value>) -> Option<OutboundMessage> {
+        if items.is_empty() {
+            return Some(OutboundMessage::Single(Self::error_response(
+                None,
+                -32600,
+                "Invalid Request",
+            )));
+        }
+
+        let mut responses = Vec::new();
+        for item in items {
+            match serde_json::from_value::<Request>(item) {
                 Ok(request) => {
-                    let response = self.handle_request(request).await;
-                    let response_str = serde_json::to_string(&response)?;
-                    
-                    writeln!(stdout, "{}", response_str)?;
-                    stdout.flush()?;
-                    
-                    debug!("Sent: {}", response_str);
+                    if let Some(response) = self.handle_request(request).await {
+                        responses.push(response);
+                    }
                 }
                 Err(e) => {
-                    warn!("Failed to parse request: {}", e);
-                    let error_response = Response {
-                        jsonrpc: "2.0".to_string(),
-                        id: None,
-                        result: None,
-                        error: Some(ErrorResponse {
-                            code: -32700,
-                            message: "Parse error".to_string(),
-                        }),
-                    };
-                    
-                    let response_str = serde_json::to_string(&error_response)?;
-                    writeln!(stdout, "{}", response_str)?;
-                    stdout.flush()?;
+                    warn!("Invalid request in batch: {}", e);
+                    responses.push(Self::error_response(None, -32600, "Invalid Request"));
                 }
             }
         }
-        
+
+        if responses.is_empty() {
+            None
+        } else {
+            Some(OutboundMessage::Batch(responses))
+        }
+    }
+
+    fn error_response(id: Option<serde_json::Value>, code: i32, message: &str) -> Response {
+        Self::error_response_with_data(id, code, message, None)
+    }
+
+    fn error_response_with_data(
+        id: Option<serde_json::Value>,
+        code: i32,
+        message: &str,
+        data: Option<serde_json::Value>,
+    ) -> Response {
+        Response {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(ErrorResponse {
+                code,
+                message: message.to_string(),
+                data,
+            }),
+        }
+    }
+
+    /// Writes a single JSON-RPC message (response or notification) to stdout,
+    /// framed by the configured `Framing`. Goes through the shared `writer` lock so outbound
+    /// responses and server-initiated notifications never interleave their
+    /// bytes on the wire.
+    async fn write_message<T: Serialize>(&self, message: &T) -> Result<()> {
+        let message_str = serde_json::to_string(message)?;
+        let mut stdout = self.writer.lock().await;
+        self.framing.write_message(&mut *stdout, &message_str)?;
+        debug!("Sent: {}", message_str);
         Ok(())
     }
 
-    async fn handle_request(&self, request: Request) -> Response {
-        match request.method.as_str() {
+    /// Registers a new subscription to `uri` and returns its `subscriptionId`.
+    /// While `uri` has a subscription, [`MCPServer::notify_resource_updated`]
+    /// emits a `notifications/resources/updated` notification to the client.
+    async fn handle_subscribe_resource(
+        &self,
+        id: Option<serde_json::Value>,
+        params: Option<serde_json::Value>,
+    ) -> Response {
+        let uri = match params.as_ref().and_then(|p| p["uri"].as_str()) {
+            Some(uri) => uri.to_string(),
+            None => return Self::error_response(id, -32602, "Missing 'uri' parameter"),
+        };
+
+        let subscription_id = self
+            .next_subscription_id
+            .fetch_add(1, Ordering::Relaxed)
+            .to_string();
+
+        let mut subscriptions = self.subscriptions.lock().await;
+        subscriptions
+            .entry(uri)
+            .or_insert_with(HashSet::new)
+            .insert(subscription_id.clone());
+
+        Response {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(serde_json::json!({ "subscriptionId": subscription_id })),
+            error: None,
+        }
+    }
+
+    async fn handle_unsubscribe_resource(
+        &self,
+        id: Option<serde_json::Value>,
+        params: Option<serde_json::Value>,
+    ) -> Response {
+        let uri = match params.as_ref().and_then(|p| p["uri"].as_str()) {
+            Some(uri) => uri.to_string(),
+            None => return Self::error_response(id, -32602, "Missing 'uri' parameter"),
+        };
+        let subscription_id = params.as_ref().and_then(|p| p["subscriptionId"].as_str());
+
+        let mut subscriptions = self.subscriptions.lock().await;
+        if let Some(subscribers) = subscriptions.get_mut(&uri) {
+            match subscription_id {
+                Some(subscription_id) => {
+                    subscribers.remove(subscription_id);
+                }
+                None => subscribers.clear(),
+            }
+            if subscribers.is_empty() {
+                subscriptions.remove(&uri);
+            }
+        }
+
+        Response {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(serde_json::json!({})),
+            error: None,
+        }
+    }
+
+    /// Pushes a `notifications/resources/updated` notification to every
+    /// subscriber of `uri`. Notifications are JSON-RPC messages without an
+    /// `id`, so callers must not expect a reply. Intended to be called from
+    /// tool/resource code once a watched resource's contents change.
+    pub async fn notify_resource_updated(&self, uri: &str) -> Result<()> {
+        let has_subscribers = self.subscriptions.lock().await.contains_key(uri);
+        if !has_subscribers {
+            return Ok(());
+        }
+
+        let notification = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/resources/updated",
+            "params": { "uri": uri }
+        });
+        self.write_message(&notification).await
+    }
+
+    /// Dispatches a single request to its handler. A request whose `id` is
+    /// absent is a *notification* (e.g. `notifications/initialized`): its
+    /// side effect still runs, but the response is suppressed and `None` is
+    /// returned so the caller emits nothing for it.
+    async fn handle_request(&self, request: Request) -> Option<Response> {
+        let is_notification = request.id.is_none();
+
+        let response = match request.method.as_str() {
             "initialize" => self.handle_initialize(request.id),
             "tools/list" => self.handle_list_tools(request.id),
             "tools/call" => self.handle_call_tool(request.id, request.params).await,
             "resources/list" => self.handle_list_resources(request.id),
             "resources/read" => self.handle_read_resource(request.id, request.params).await,
-            _ => Response {
+            "resources/subscribe" => {
+                self.handle_subscribe_resource(request.id, request.params)
+                    .await
+            }
+            "resources/unsubscribe" => {
+                self.handle_unsubscribe_resource(request.id, request.params)
+                    .await
+            }
+            "notifications/initialized" => Response {
                 jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: None,
-                error: Some(ErrorResponse {
-                    code: -32601,
-                    message: format!("Method not found: {}", request.method),
-                }),
+                id: None,
+                result: Some(serde_json::json!({})),
+                error: None,
             },
+            _ => Self::error_response(
+                request.id,
+                -32601,
+                &format!("Method not found: {}", request.method),
+            ),
+        };
+
+        if is_notification {
+            None
+        } else {
+            Some(response)
         }
     }
 
@@ -113,7 +411,9 @@ impl MCPServer {
                 "protocolVersion": "1.0",
                 "capabilities": {
                     "tools": {},
-                    "resources": {}
+                    "resources": {
+                        "subscribe": true
+                    }
                 },
                 "serverInfo": {
                     "name": self.name,
@@ -142,34 +442,36 @@ impl MCPServer {
     ) -> Response {
         match params {
             Some(params) => match tools::call_tool(params).await {
-                Ok(result) => Response {
-                    jsonrpc: "2.0".to_string(),
-                    id,
-                    result: Some(result),
-                    error: None,
-                },
-                Err(e) => Response {
-                    jsonrpc: "2.0".to_string(),
-                    id,
-                    result: None,
-                    error: Some(ErrorResponse {
-                        code: -32603,
-                        message: e.to_string(),
-                    }),
-                },
-            },
-            None => Response {
-                jsonrpc: "2.0".to_string(),
-                id,
-                result: None,
-                error: Some(ErrorResponse {
-                    code: -32602,
-                    message: "Invalid params".to_string(),
-                }),
+                Ok(result) => {
+                    // A tool reports that it changed a resource via this
+                    // well-known field, since it only sees `arguments`, not
+                    // a handle back to the server. See `tools::touch_resource`.
+                    if let Some(uri) = result.get("updatedResourceUri").and_then(|v| v.as_str()) {
+                        if let Err(e) = self.notify_resource_updated(uri).await {
+                            warn!("Failed to notify resource update for {}: {}", uri, e);
+                        }
+                    }
+                    Response {
+                        jsonrpc: "2.0".to_string(),
+                        id,
+                        result: Some(result),
+                        error: None,
+                    }
+                }
+                Err(e) => Self::tool_error_response(id, &e),
             },
+            None => Self::error_response(id, -32602, "Invalid params"),
         }
     }
 
+    /// Maps a [`ToolError`] to its proper JSON-RPC code (`-32602` for bad
+    /// params, `-32601` for an unknown tool, `-32603` otherwise) instead of
+    /// collapsing every failure into "internal error", and forwards any
+    /// structured `data` the error carries.
+    fn tool_error_response(id: Option<serde_json::Value>, error: &ToolError) -> Response {
+        Self::error_response_with_data(id, error.code(), &error.to_string(), error.data())
+    }
+
     fn handle_list_resources(&self, id: Option<serde_json::Value>) -> Response {
         Response {
             jsonrpc: "2.0".to_string(),
@@ -194,25 +496,40 @@ impl MCPServer {
                     result: Some(result),
                     error: None,
                 },
-                Err(e) => Response {
-                    jsonrpc: "2.0".to_string(),
-                    id,
-                    result: None,
-                    error: Some(ErrorResponse {
-                        code: -32603,
-                        message: e.to_string(),
-                    }),
-                },
-            },
-            None => Response {
-                jsonrpc: "2.0".to_string(),
-                id,
-                result: None,
-                error: Some(ErrorResponse {
-                    code: -32602,
-                    message: "Invalid params".to_string(),
-                }),
+                Err(e) => Self::tool_error_response(id, &e),
             },
+            None => Self::error_response(id, -32602, "Invalid params"),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn empty_batch_is_invalid_request() {
+        let server = MCPServer::new();
+
+        match server.handle_batch(vec![]).await {
+            Some(OutboundMessage::Single(response)) => {
+                assert_eq!(response.error.unwrap().code, -32600);
+            }
+            other => panic!(
+                "expected a single Invalid Request response, got {:?}",
+                other.is_some()
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_of_only_notifications_produces_no_output() {
+        let server = MCPServer::new();
+        let items = vec![serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/initialized"
+        })];
+
+        assert!(server.handle_batch(items).await.is_none());
+    }
+}
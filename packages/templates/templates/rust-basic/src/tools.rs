@@ -1,85 +1,143 @@
-use anyhow::{Result, anyhow};
-use serde_json::{json, Value};
+use anyhow::Result;
+use serde_json::Value;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 use tracing::info;
+use {{serverName}}_macros::mcp_tool;
+
+/// One tool registered via `#[mcp_tool]`: its `inputSchema`/`description`
+/// entry for `list_tools()`, plus the dispatch closure `call_tool()` invokes
+/// with the caller's `arguments` object. Populated at compile time by
+/// `inventory::submit!` inside the macro expansion, so the schema and the
+/// handler can never drift apart.
+pub struct ToolDef {
+    pub name: &'static str,
+    pub schema: fn() -> Value,
+    pub handler: fn(&Value) -> Pin<Box<dyn Future<Output = Result<Value, ToolError>> + Send>>,
+}
+
+inventory::collect!(ToolDef);
+
+/// Distinguishes *why* a tool call failed so `server::handle_call_tool` can
+/// map it to the correct JSON-RPC code (`-32602` bad params, `-32601`
+/// unknown tool, `-32603` genuine internal fault) instead of collapsing
+/// every failure into "internal error".
+#[derive(Debug)]
+pub enum ToolError {
+    /// The caller's `arguments` were missing or the wrong shape. `data`
+    /// carries the offending field so a client can point a user at it.
+    InvalidParams { message: String, data: Value },
+    /// No tool is registered under that name (or, from
+    /// `resources::read_resource`, no resource under that URI).
+    NotFound(String),
+    /// Anything else — a genuine internal fault raised by the tool body.
+    Internal(anyhow::Error),
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolError::InvalidParams { message, .. } => write!(f, "{}", message),
+            ToolError::NotFound(name) => write!(f, "Not found: {}", name),
+            ToolError::Internal(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+impl From<anyhow::Error> for ToolError {
+    fn from(err: anyhow::Error) -> Self {
+        ToolError::Internal(err)
+    }
+}
+
+impl ToolError {
+    /// Also used by `resources::read_resource`, which reuses `ToolError`
+    /// rather than a parallel `ResourceError` enum.
+    pub fn invalid_param(field: &str, message: impl Into<String>) -> Self {
+        ToolError::InvalidParams {
+            message: message.into(),
+            data: serde_json::json!({ "field": field }),
+        }
+    }
+
+    /// JSON-RPC error code this failure should be reported as.
+    pub fn code(&self) -> i32 {
+        match self {
+            ToolError::InvalidParams { .. } => -32602,
+            ToolError::NotFound(_) => -32601,
+            ToolError::Internal(_) => -32603,
+        }
+    }
+
+    /// Structured `error.data` payload, if any.
+    pub fn data(&self) -> Option<Value> {
+        match self {
+            ToolError::InvalidParams { data, .. } => Some(data.clone()),
+            _ => None,
+        }
+    }
+}
 
 pub fn list_tools() -> Vec<Value> {
-    vec![
-        json!({
-            "name": "hello",
-            "description": "Say hello to someone",
-            "inputSchema": {
-                "type": "object",
-                "properties": {
-                    "name": {
-                        "type": "string",
-                        "description": "Name to greet"
-                    }
-                },
-                "required": ["name"]
-            }
-        }),
-        json!({
-            "name": "add",
-            "description": "Add two numbers",
-            "inputSchema": {
-                "type": "object",
-                "properties": {
-                    "a": {
-                        "type": "number",
-                        "description": "First number"
-                    },
-                    "b": {
-                        "type": "number",
-                        "description": "Second number"
-                    }
-                },
-                "required": ["a", "b"]
-            }
-        }),
-    ]
+    inventory::iter::<ToolDef>().map(|tool| (tool.schema)()).collect()
 }
 
-pub async fn call_tool(params: Value) -> Result<Value> {
+pub async fn call_tool(params: Value) -> Result<Value, ToolError> {
     let name = params["name"]
         .as_str()
-        .ok_or_else(|| anyhow!("Missing tool name"))?;
-    
-    let arguments = params.get("arguments")
-        .ok_or_else(|| anyhow!("Missing tool arguments"))?;
-    
+        .ok_or_else(|| ToolError::invalid_param("name", "Missing tool name"))?;
+
+    let arguments = params
+        .get("arguments")
+        .ok_or_else(|| ToolError::invalid_param("arguments", "Missing tool arguments"))?;
+
     info!("Calling tool: {} with args: {:?}", name, arguments);
-    
-    match name {
-        "hello" => {
-            let name = arguments["name"]
-                .as_str()
-                .ok_or_else(|| anyhow!("Missing name parameter"))?;
-            
-            Ok(json!({
-                "content": [{
-                    "type": "text",
-                    "text": format!("Hello, {}! This is {{serverName}} speaking.", name)
-                }]
-            }))
-        }
-        "add" => {
-            let a = arguments["a"]
-                .as_f64()
-                .ok_or_else(|| anyhow!("Parameter 'a' must be a number"))?;
-            
-            let b = arguments["b"]
-                .as_f64()
-                .ok_or_else(|| anyhow!("Parameter 'b' must be a number"))?;
-            
-            let result = a + b;
-            
-            Ok(json!({
-                "content": [{
-                    "type": "text",
-                    "text": format!("{} + {} = {}", a, b, result)
-                }]
-            }))
-        }
-        _ => Err(anyhow!("Unknown tool: {}", name)),
-    }
-}
\ No newline at end of file
+
+    let tool = inventory::iter::<ToolDef>()
+        .find(|tool| tool.name == name)
+        .ok_or_else(|| ToolError::NotFound(name.to_string()))?;
+
+    (tool.handler)(arguments).await
+}
+
+#[mcp_tool(description = "Say hello to someone")]
+async fn hello(name: String) -> Result<Value> {
+    Ok(serde_json::json!({
+        "content": [{
+            "type": "text",
+            "text": format!("Hello, {}! This is {{serverName}} speaking.", name)
+        }]
+    }))
+}
+
+#[mcp_tool(description = "Add two numbers")]
+async fn add(a: f64, b: f64) -> Result<Value> {
+    let result = a + b;
+
+    Ok(serde_json::json!({
+        "content": [{
+            "type": "text",
+            "text": format!("{} + {} = {}", a, b, result)
+        }]
+    }))
+}
+
+/// Example of a tool that mutates a resource. A tool can't call
+/// `MCPServer::notify_resource_updated` directly (it only has the
+/// JSON-RPC `arguments`, not a handle to the server), so it reports the
+/// change back via a well-known `updatedResourceUri` field instead;
+/// `server::handle_call_tool` reads that field after a successful call and
+/// emits the `notifications/resources/updated` notification to any subscribers.
+#[mcp_tool(description = "Mark the example greeting resource as updated")]
+async fn touch_resource() -> Result<Value> {
+    Ok(serde_json::json!({
+        "content": [{
+            "type": "text",
+            "text": "Marked example://greeting as updated"
+        }],
+        "updatedResourceUri": "example://greeting"
+    }))
+}
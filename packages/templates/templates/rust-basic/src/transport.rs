@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, Write};
+
+/// A pluggable base protocol for framing JSON-RPC messages on the wire.
+///
+/// `{{serverName}}` defaults to newline-delimited JSON, but some hosts (LSP
+/// clients, editors) speak the `Content-Length`-prefixed framing instead.
+/// Implementations are stateless parsers over whatever reader/writer the
+/// caller hands them, so a single `Box<dyn Framing>` can be shared across
+/// the whole connection.
+pub trait Framing: Send + Sync {
+    /// Short name used in startup logs.
+    fn name(&self) -> &'static str;
+
+    /// Reads the next message payload, or `Ok(None)` on a clean EOF.
+    fn read_message(&self, reader: &mut dyn BufRead) -> Result<Option<String>>;
+
+    /// Writes a single message payload, applying this framing's wire format.
+    fn write_message(&self, writer: &mut dyn Write, payload: &str) -> Result<()>;
+}
+
+/// One JSON value per line, separated by `\n`. The format the generated
+/// server has always spoken over stdio.
+pub struct NdjsonFraming;
+
+impl Framing for NdjsonFraming {
+    fn name(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn read_message(&self, reader: &mut dyn BufRead) -> Result<Option<String>> {
+        let mut line = String::new();
+        let bytes_read = reader.read_line(&mut line)?;
+        if bytes_read == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    fn write_message(&self, writer: &mut dyn Write, payload: &str) -> Result<()> {
+        writeln!(writer, "{}", payload)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// LSP-style `Content-Length: N\r\n\r\n<payload>` framing, as used by
+/// editor LSP clients and rust-analyzer's own message loop. Lets the
+/// generated server talk to header-framed hosts without touching any tool
+/// code.
+pub struct ContentLengthFraming;
+
+impl Framing for ContentLengthFraming {
+    fn name(&self) -> &'static str {
+        "content-length"
+    }
+
+    fn read_message(&self, reader: &mut dyn BufRead) -> Result<Option<String>> {
+        let mut content_length: Option<usize> = None;
+
+        loop {
+            let mut header_line = String::new();
+            let bytes_read = reader.read_line(&mut header_line)?;
+            if bytes_read == 0 {
+                return Ok(None);
+            }
+
+            let header_line = header_line.trim_end_matches(['\r', '\n']);
+            if header_line.is_empty() {
+                break;
+            }
+
+            if let Some((key, value)) = header_line.split_once(':') {
+                if key.trim().eq_ignore_ascii_case("Content-Length") {
+                    content_length = Some(
+                        value
+                            .trim()
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid Content-Length header: {}", value))?,
+                    );
+                }
+            }
+        }
+
+        let content_length =
+            content_length.ok_or_else(|| anyhow!("Missing Content-Length header"))?;
+
+        let mut payload = vec![0u8; content_length];
+        reader.read_exact(&mut payload)?;
+        Ok(Some(String::from_utf8(payload)?))
+    }
+
+    fn write_message(&self, writer: &mut dyn Write, payload: &str) -> Result<()> {
+        write!(
+            writer,
+            "Content-Length: {}\r\n\r\n{}",
+            payload.len(),
+            payload
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Picks the framing to speak based on the `MCP_FRAMING` environment
+/// variable (`content-length`/`lsp`, otherwise the `ndjson` default).
+pub fn framing_from_env() -> Box<dyn Framing> {
+    match std::env::var("MCP_FRAMING").as_deref() {
+        Ok("content-length") | Ok("lsp") => Box::new(ContentLengthFraming),
+        _ => Box::new(NdjsonFraming),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn ndjson_reads_one_line_at_a_time() {
+        let framing = NdjsonFraming;
+        let mut reader = Cursor::new(b"{\"a\":1}\n{\"b\":2}\n".to_vec());
+
+        assert_eq!(
+            framing.read_message(&mut reader).unwrap(),
+            Some("{\"a\":1}".to_string())
+        );
+        assert_eq!(
+            framing.read_message(&mut reader).unwrap(),
+            Some("{\"b\":2}".to_string())
+        );
+        assert_eq!(framing.read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn ndjson_writes_with_trailing_newline() {
+        let framing = NdjsonFraming;
+        let mut writer = Vec::new();
+        framing.write_message(&mut writer, "{\"a\":1}").unwrap();
+        assert_eq!(writer, b"{\"a\":1}\n");
+    }
+
+    #[test]
+    fn content_length_reads_header_and_payload() {
+        let framing = ContentLengthFraming;
+        let mut reader = Cursor::new(b"Content-Length: 7\r\n\r\n{\"a\":1}".to_vec());
+
+        assert_eq!(
+            framing.read_message(&mut reader).unwrap(),
+            Some("{\"a\":1}".to_string())
+        );
+    }
+
+    #[test]
+    fn content_length_rejects_missing_header() {
+        let framing = ContentLengthFraming;
+        let mut reader = Cursor::new(b"\r\n{\"a\":1}".to_vec());
+
+        assert!(framing.read_message(&mut reader).is_err());
+    }
+
+    #[test]
+    fn content_length_writes_header_and_payload() {
+        let framing = ContentLengthFraming;
+        let mut writer = Vec::new();
+        framing.write_message(&mut writer, "{\"a\":1}").unwrap();
+        assert_eq!(writer, b"Content-Length: 7\r\n\r\n{\"a\":1}");
+    }
+}